@@ -0,0 +1,523 @@
+//! MetadataSource abstracts the read path `playlist::items()` depends on: listing a playlist's
+//! entries and fetching per-video live-streaming/content details. `YouTubeSource` wraps the
+//! authenticated YouTube Data API hub; `InvidiousSource` talks to a public Invidious instance
+//! instead, so a playlist can be read without an API key or quota. Playlist mutation (`sort`'s
+//! update, `prune`'s delete) is not part of this trait and always goes through the YouTube hub
+//! directly, since Invidious has no authenticated write API.
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use google_youtube3::YouTube;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+pub type SourceResult<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// PlaylistEntry is the information available from listing a playlist, before per-video details
+/// have been fetched.
+#[derive(Clone, Debug)]
+pub struct PlaylistEntry {
+    pub video_id: String,
+    pub playlist_item_id: String,
+    pub title: String,
+}
+
+/// VideoDetails is the per-video information `video_details` enriches a `PlaylistEntry` with.
+#[derive(Clone, Default, Debug)]
+pub struct VideoDetails {
+    pub scheduled_start_time: Option<DateTime<FixedOffset>>,
+    pub actual_start_time: Option<DateTime<FixedOffset>>,
+    pub blocked: bool,
+}
+
+/// ItemError records why a single playlist item or video could not be fully parsed, so that one
+/// malformed entry doesn't abort an entire `items()` call.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ItemError {
+    pub video_id: String,
+    pub playlist_item_id: String,
+    pub reason: String,
+}
+
+/// MetadataSource is the read-only enrichment path `items()` depends on. Implementations are
+/// free to batch, cache or rate-limit internally; video ids absent from `video_details`'s result
+/// are treated as deleted.
+#[async_trait]
+pub trait MetadataSource {
+    /// playlist_items lists every entry in the given playlist, paging as needed. `YouTubeSource`
+    /// reports items that can't be parsed in the returned error list rather than failing the
+    /// call; `InvidiousSource` decodes the playlist response as a whole, so a single malformed
+    /// entry fails the call and its error list is always empty.
+    async fn playlist_items(&self, playlist_id: &str) -> SourceResult<(Vec<PlaylistEntry>, Vec<ItemError>)>;
+
+    /// video_details fetches live-streaming and content details for the given video ids,
+    /// batching internally as the backend allows. Ids absent from the result map are videos the
+    /// backend could not find (most likely deleted).
+    async fn video_details(
+        &self,
+        video_ids: &[String],
+    ) -> SourceResult<(HashMap<String, VideoDetails>, Vec<ItemError>)>;
+
+    /// supports_mutation reports whether `playlist_item_id`s returned by `playlist_items` are
+    /// real YouTube playlist item ids that `sort`/`prune` can safely pass to the YouTube hub's
+    /// `update`/`delete` calls. Sources that populate `playlist_item_id` with a placeholder (e.g.
+    /// `InvidiousSource`) must override this to `false`.
+    fn supports_mutation(&self) -> bool {
+        true
+    }
+}
+
+/// YouTubeSource serves MetadataSource reads from the authenticated YouTube Data API, the same
+/// hub used for playlist mutation elsewhere.
+pub struct YouTubeSource {
+    hub: YouTube,
+}
+
+impl YouTubeSource {
+    pub fn new(hub: YouTube) -> Self {
+        YouTubeSource { hub }
+    }
+}
+
+#[async_trait]
+impl MetadataSource for YouTubeSource {
+    async fn playlist_items(&self, playlist_id: &str) -> SourceResult<(Vec<PlaylistEntry>, Vec<ItemError>)> {
+        let mut entries = vec![];
+        let mut errs = vec![];
+
+        let (_, mut res) = playlist_items_page(&self.hub, playlist_id, &None).await?;
+        while let Some(items) = &res.items {
+            for item in items {
+                match parse_playlist_item(item) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => errs.push(e),
+                }
+            }
+            if res.next_page_token.is_some() {
+                res = playlist_items_page(&self.hub, playlist_id, &res.next_page_token)
+                    .await?
+                    .1;
+            } else {
+                res.items = None;
+            }
+        }
+
+        Ok((entries, errs))
+    }
+
+    async fn video_details(
+        &self,
+        video_ids: &[String],
+    ) -> SourceResult<(HashMap<String, VideoDetails>, Vec<ItemError>)> {
+        let mut details = HashMap::new();
+        let mut errs = vec![];
+
+        for chunk in video_ids.chunks(50) {
+            let mut req = self.hub.videos().list(&vec![
+                "liveStreamingDetails".into(),
+                "contentDetails".into(),
+            ]);
+            for id in chunk {
+                req = req.add_id(id);
+            }
+            let (_, v) = req.doit().await?;
+            for video in v.items.unwrap_or_default() {
+                match parse_video(&video) {
+                    Ok((video_id, d, mut video_errs)) => {
+                        errs.append(&mut video_errs);
+                        details.insert(video_id, d);
+                    }
+                    Err(e) => errs.push(e),
+                }
+            }
+        }
+
+        Ok((details, errs))
+    }
+}
+
+async fn playlist_items_page(
+    hub: &YouTube,
+    playlist_id: &str,
+    next_page_token: &Option<String>,
+) -> google_youtube3::client::Result<(
+    hyper::Response<hyper::body::Body>,
+    google_youtube3::api::PlaylistItemListResponse,
+)> {
+    let mut req = hub
+        .playlist_items()
+        .list(&vec![
+            "snippet".into(),
+            "id".into(),
+            "contentDetails".into(),
+        ])
+        .playlist_id(playlist_id);
+    if let Some(next) = next_page_token {
+        req = req.page_token(&next);
+    }
+    req.doit().await
+}
+
+/// parse_playlist_item extracts a PlaylistEntry from a single API `PlaylistItem`, or an ItemError
+/// describing whichever required field was missing.
+fn parse_playlist_item(
+    item: &google_youtube3::api::PlaylistItem,
+) -> std::result::Result<PlaylistEntry, ItemError> {
+    let playlist_item_id = match item.id.as_ref() {
+        Some(id) => id.to_owned(),
+        None => {
+            return Err(ItemError {
+                video_id: String::new(),
+                playlist_item_id: String::new(),
+                reason: "playlist item has no id".to_owned(),
+            })
+        }
+    };
+    let video_id = match item
+        .content_details
+        .as_ref()
+        .and_then(|d| d.video_id.as_ref())
+    {
+        Some(id) => id.to_owned(),
+        None => {
+            return Err(ItemError {
+                video_id: String::new(),
+                playlist_item_id,
+                reason: "missing contentDetails.videoId".to_owned(),
+            })
+        }
+    };
+    let title = match item.snippet.as_ref().and_then(|s| s.title.as_ref()) {
+        Some(title) => title.to_owned(),
+        None => {
+            return Err(ItemError {
+                video_id,
+                playlist_item_id,
+                reason: "missing snippet.title".to_owned(),
+            })
+        }
+    };
+    Ok(PlaylistEntry {
+        video_id,
+        playlist_item_id,
+        title,
+    })
+}
+
+/// parse_video extracts a video id and its VideoDetails from a single API `Video`, along with any
+/// non-fatal errors encountered along the way (e.g. an unparseable timestamp, which simply leaves
+/// the corresponding field unset rather than discarding the whole video). Returns an ItemError
+/// outright only when the video has no id at all, since nothing useful can be recorded without it.
+fn parse_video(
+    video: &google_youtube3::api::Video,
+) -> std::result::Result<(String, VideoDetails, Vec<ItemError>), ItemError> {
+    let video_id = match video.id.as_ref() {
+        Some(id) => id.to_owned(),
+        None => {
+            return Err(ItemError {
+                video_id: String::new(),
+                playlist_item_id: String::new(),
+                reason: "video response has no id".to_owned(),
+            })
+        }
+    };
+
+    let mut errs = vec![];
+    let mut d = VideoDetails::default();
+    if let Some(ls) = video.live_streaming_details.as_ref() {
+        d.scheduled_start_time = match ls.scheduled_start_time.as_ref() {
+            Some(t) => match DateTime::parse_from_rfc3339(t) {
+                Ok(dt) => Some(dt),
+                Err(e) => {
+                    errs.push(ItemError {
+                        video_id: video_id.clone(),
+                        playlist_item_id: String::new(),
+                        reason: format!("invalid scheduledStartTime: {}", e),
+                    });
+                    None
+                }
+            },
+            None => None,
+        };
+        d.actual_start_time = match ls.actual_start_time.as_ref() {
+            Some(t) => match DateTime::parse_from_rfc3339(t) {
+                Ok(dt) => Some(dt),
+                Err(e) => {
+                    errs.push(ItemError {
+                        video_id: video_id.clone(),
+                        playlist_item_id: String::new(),
+                        reason: format!("invalid actualStartTime: {}", e),
+                    });
+                    None
+                }
+            },
+            None => None,
+        };
+    }
+    if let Some(cd) = video.content_details.as_ref() {
+        if let Some(restriction) = cd.region_restriction.as_ref() {
+            if let Some(blocked) = restriction.blocked.as_ref() {
+                d.blocked = !blocked.is_empty();
+            }
+        }
+    }
+
+    Ok((video_id, d, errs))
+}
+
+/// InvidiousSource serves MetadataSource reads from a public Invidious instance, requiring
+/// neither an API key nor quota. Because Invidious has no concept of an authenticated playlist
+/// item id, `playlist_item_id` is populated with the video id; a Playlist built on this source is
+/// therefore only suitable for read-only inspection, not for `sort`/`prune`, which need the real
+/// playlist item id to mutate the (YouTube-hosted) playlist. `supports_mutation` returns `false`
+/// so a Playlist enforces this instead of relying on callers to remember it.
+pub struct InvidiousSource {
+    instance_url: String,
+    client: reqwest::Client,
+}
+
+impl InvidiousSource {
+    pub fn new(instance_url: &str) -> Self {
+        InvidiousSource {
+            instance_url: instance_url.trim_end_matches('/').to_owned(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct InvidiousPlaylistVideo {
+    #[serde(rename = "videoId")]
+    video_id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct InvidiousPlaylist {
+    videos: Vec<InvidiousPlaylistVideo>,
+}
+
+#[derive(Deserialize)]
+struct InvidiousVideo {
+    #[serde(rename = "videoId")]
+    video_id: String,
+    #[serde(rename = "premiereTimestamp")]
+    premiere_timestamp: Option<i64>,
+    #[serde(rename = "liveNow")]
+    live_now: Option<bool>,
+}
+
+#[async_trait]
+impl MetadataSource for InvidiousSource {
+    async fn playlist_items(&self, playlist_id: &str) -> SourceResult<(Vec<PlaylistEntry>, Vec<ItemError>)> {
+        let url = format!("{}/api/v1/playlists/{}", self.instance_url, playlist_id);
+        let playlist: InvidiousPlaylist = self.client.get(&url).send().await?.json().await?;
+        let entries = playlist
+            .videos
+            .into_iter()
+            .map(|v| PlaylistEntry {
+                playlist_item_id: v.video_id.clone(),
+                video_id: v.video_id,
+                title: v.title,
+            })
+            .collect();
+        Ok((entries, vec![]))
+    }
+
+    async fn video_details(
+        &self,
+        video_ids: &[String],
+    ) -> SourceResult<(HashMap<String, VideoDetails>, Vec<ItemError>)> {
+        let mut details = HashMap::new();
+        let errs = vec![];
+
+        // Invidious has no batch-by-id endpoint, so fetch one video at a time.
+        for video_id in video_ids {
+            let url = format!("{}/api/v1/videos/{}", self.instance_url, video_id);
+            let resp = self.client.get(&url).send().await?;
+            if !resp.status().is_success() {
+                // Most likely deleted or unavailable on this instance; leave it absent so the
+                // caller treats it the same way as a missing YouTube videos().list() result.
+                continue;
+            }
+            let video: InvidiousVideo = resp.json().await?;
+            let video_id = video.video_id.clone();
+            details.insert(video_id, invidious_video_to_details(&video));
+        }
+
+        Ok((details, errs))
+    }
+
+    fn supports_mutation(&self) -> bool {
+        false
+    }
+}
+
+/// invidious_video_to_details maps Invidious's single premiere/scheduled timestamp plus `liveNow`
+/// flag onto the distinct scheduled/actual start times the rest of the codebase expects: a live
+/// video's premiere timestamp is treated as its actual start too.
+fn invidious_video_to_details(video: &InvidiousVideo) -> VideoDetails {
+    let mut d = VideoDetails::default();
+    if let Some(ts) = video.premiere_timestamp {
+        let start = DateTime::from_timestamp(ts, 0)
+            .map(|dt| dt.with_timezone(&FixedOffset::east_opt(0).unwrap()));
+        d.scheduled_start_time = start;
+        if video.live_now.unwrap_or(false) {
+            d.actual_start_time = start;
+        }
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use google_youtube3::api::{
+        PlaylistItem, PlaylistItemContentDetails, PlaylistItemSnippet, Video, VideoContentDetails,
+        VideoContentDetailsRegionRestriction, VideoLiveStreamingDetails,
+    };
+
+    #[test]
+    fn invidious_source_does_not_support_mutation() {
+        assert!(!InvidiousSource::new("https://example.invidious").supports_mutation());
+    }
+
+    #[test]
+    fn parse_playlist_item_ok() {
+        let item = PlaylistItem {
+            id: Some("pii1".to_owned()),
+            content_details: Some(PlaylistItemContentDetails {
+                video_id: Some("v1".to_owned()),
+                ..Default::default()
+            }),
+            snippet: Some(PlaylistItemSnippet {
+                title: Some("Some Title".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let entry = parse_playlist_item(&item).unwrap();
+        assert_eq!(entry.video_id, "v1");
+        assert_eq!(entry.playlist_item_id, "pii1");
+        assert_eq!(entry.title, "Some Title");
+    }
+
+    #[test]
+    fn parse_playlist_item_missing_id() {
+        let item = PlaylistItem::default();
+        let err = parse_playlist_item(&item).unwrap_err();
+        assert_eq!(err.reason, "playlist item has no id");
+    }
+
+    #[test]
+    fn parse_playlist_item_missing_video_id() {
+        let item = PlaylistItem {
+            id: Some("pii1".to_owned()),
+            ..Default::default()
+        };
+        let err = parse_playlist_item(&item).unwrap_err();
+        assert_eq!(err.playlist_item_id, "pii1");
+        assert_eq!(err.reason, "missing contentDetails.videoId");
+    }
+
+    #[test]
+    fn parse_playlist_item_missing_title() {
+        let item = PlaylistItem {
+            id: Some("pii1".to_owned()),
+            content_details: Some(PlaylistItemContentDetails {
+                video_id: Some("v1".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let err = parse_playlist_item(&item).unwrap_err();
+        assert_eq!(err.video_id, "v1");
+        assert_eq!(err.reason, "missing snippet.title");
+    }
+
+    #[test]
+    fn parse_video_missing_id() {
+        let video = Video::default();
+        let err = parse_video(&video).unwrap_err();
+        assert_eq!(err.reason, "video response has no id");
+    }
+
+    #[test]
+    fn parse_video_ok() {
+        let video = Video {
+            id: Some("v1".to_owned()),
+            live_streaming_details: Some(VideoLiveStreamingDetails {
+                scheduled_start_time: Some("2021-09-30T10:55:00+01:00".to_owned()),
+                actual_start_time: Some("2021-09-30T10:56:00+01:00".to_owned()),
+                ..Default::default()
+            }),
+            content_details: Some(VideoContentDetails {
+                region_restriction: Some(VideoContentDetailsRegionRestriction {
+                    blocked: Some(vec!["US".to_owned()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let (video_id, details, errs) = parse_video(&video).unwrap();
+        assert_eq!(video_id, "v1");
+        assert!(errs.is_empty());
+        assert!(details.scheduled_start_time.is_some());
+        assert!(details.actual_start_time.is_some());
+        assert!(details.blocked);
+    }
+
+    #[test]
+    fn parse_video_invalid_timestamp_is_reported_not_fatal() {
+        let video = Video {
+            id: Some("v1".to_owned()),
+            live_streaming_details: Some(VideoLiveStreamingDetails {
+                scheduled_start_time: Some("not-a-timestamp".to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let (video_id, details, errs) = parse_video(&video).unwrap();
+        assert_eq!(video_id, "v1");
+        assert_eq!(errs.len(), 1);
+        assert!(errs[0].reason.contains("invalid scheduledStartTime"));
+        assert!(details.scheduled_start_time.is_none());
+    }
+
+    #[test]
+    fn invidious_video_to_details_premiere_not_live() {
+        let video = InvidiousVideo {
+            video_id: "v1".to_owned(),
+            premiere_timestamp: Some(1_633_000_000),
+            live_now: Some(false),
+        };
+        let d = invidious_video_to_details(&video);
+        assert!(d.scheduled_start_time.is_some());
+        assert!(d.actual_start_time.is_none());
+    }
+
+    #[test]
+    fn invidious_video_to_details_live_now_sets_actual_start() {
+        let video = InvidiousVideo {
+            video_id: "v1".to_owned(),
+            premiere_timestamp: Some(1_633_000_000),
+            live_now: Some(true),
+        };
+        let d = invidious_video_to_details(&video);
+        assert_eq!(d.scheduled_start_time, d.actual_start_time);
+    }
+
+    #[test]
+    fn invidious_video_to_details_no_premiere() {
+        let video = InvidiousVideo {
+            video_id: "v1".to_owned(),
+            premiere_timestamp: None,
+            live_now: None,
+        };
+        let d = invidious_video_to_details(&video);
+        assert!(d.scheduled_start_time.is_none());
+        assert!(d.actual_start_time.is_none());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod metadata_source;
+pub mod playlist;
@@ -1,22 +1,86 @@
+use super::metadata_source::{ItemError, MetadataSource, VideoDetails, YouTubeSource};
 use async_trait::async_trait;
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
 use google_youtube3::{
     api::Scope,
-    api::{PlaylistItem, PlaylistItemListResponse, PlaylistItemSnippet, ResourceId},
-    client::Result,
+    api::{PlaylistItem, PlaylistItemSnippet, ResourceId},
     YouTube,
 };
-use hyper::Response;
-use std::{cmp::Ordering, fmt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    error, fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// PlaylistError is the error type for every operation in this module. It wraps whatever the
+/// active backend produced: a YouTube API error from a mutating call, or a boxed error from the
+/// `MetadataSource` used for reads (which may not be backed by the YouTube API at all).
+#[derive(Debug)]
+pub enum PlaylistError {
+    Api(google_youtube3::client::Error),
+    Source(Box<dyn error::Error + Send + Sync>),
+    Io(std::io::Error),
+    Anomaly(String),
+    ReadOnlySource,
+}
+
+impl fmt::Display for PlaylistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaylistError::Api(e) => write!(f, "{}", e),
+            PlaylistError::Source(e) => write!(f, "{}", e),
+            PlaylistError::Io(e) => write!(f, "{}", e),
+            PlaylistError::Anomaly(msg) => write!(f, "{}", msg),
+            PlaylistError::ReadOnlySource => write!(
+                f,
+                "the configured MetadataSource does not support the playlist item ids sort/prune need to mutate the playlist"
+            ),
+        }
+    }
+}
+
+impl error::Error for PlaylistError {}
+
+impl From<google_youtube3::client::Error> for PlaylistError {
+    fn from(e: google_youtube3::client::Error) -> Self {
+        PlaylistError::Api(e)
+    }
+}
+
+impl From<Box<dyn error::Error + Send + Sync>> for PlaylistError {
+    fn from(e: Box<dyn error::Error + Send + Sync>) -> Self {
+        PlaylistError::Source(e)
+    }
+}
+
+impl From<std::io::Error> for PlaylistError {
+    fn from(e: std::io::Error) -> Self {
+        PlaylistError::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, PlaylistError>;
 
 #[derive(Default, Clone, PartialEq, Debug)]
 pub struct Item {
     pub video_id: String,
     playlist_item_id: String,
     pub title: String,
+    /// normalized_title is the lowercased title with a leading English article stripped, used to
+    /// order items that have no time information. Computed once when the Item is built rather
+    /// than on every comparison in `sort_items`.
+    normalized_title: String,
     pub scheduled_start_time: Option<DateTime<FixedOffset>>,
     pub actual_start_time: Option<DateTime<FixedOffset>>,
     pub blocked: bool,
+    /// anomaly_reason explains why this item failed `check_anomaly`'s temporal invariant checks,
+    /// if at all, e.g. an actual_start_time before its scheduled_start_time.
+    anomaly_reason: Option<String>,
 }
 
 impl fmt::Display for Item {
@@ -33,109 +97,294 @@ pub trait Playlist {
     /// sort orders the playlist as follows:
     /// * streamed videos in reverse chronological order (newest first), followed
     /// * not-yet-streamed videos again in reverse chronological order (newest first), followed by
-    /// * videos for which there is no time information.
+    /// * videos for which there is no time information, alphabetically by title (ignoring a
+    ///   leading "a"/"an"/"the" and case).
+    ///
+    /// Returns `PlaylistError::ReadOnlySource` if the configured `MetadataSource` doesn't support
+    /// mutation (see `MetadataSource::supports_mutation`).
     async fn sort(self: &Self) -> Result<()>;
 
     /// prune removes any invalid videos from the playlist. These include:
     /// * deleted videos
     /// * videos for which there is no time information (e.g. with no live streaming information such as scheduled start time).
+    /// * videos flagged by `check_anomaly`, if the playlist's `AnomalyPolicy` is `Prune`.
+    ///
+    /// Like `sort` (which it calls first), returns `PlaylistError::ReadOnlySource` if the
+    /// configured `MetadataSource` doesn't support mutation.
     async fn prune(self: &Self, max_streamed: usize) -> Result<()>;
 
     // print prints the playlist to standard error.
     async fn print(self: &Self) -> Result<()>;
+
+    /// schedule_html renders the playlist as a standalone HTML page: upcoming
+    /// `scheduled_start_time` streams grouped into a day-by-day agenda, followed by a separate
+    /// list of already-streamed items. `privacy` controls how much detail is included.
+    async fn schedule_html(self: &Self, privacy: SchedulePrivacy) -> Result<String>;
 }
 
 struct PlaylistImpl {
     hub: YouTube,
+    source: Box<dyn MetadataSource + Send + Sync>,
     id: String,
     dry_run: bool,
     debug: bool,
+    cache_path: PathBuf,
+    no_cache: bool,
+    report_path: PathBuf,
+    report_format: ReportFormat,
+    anomaly_policy: AnomalyPolicy,
+}
+
+/// AnomalyPolicy controls what happens when `items()` finds a video that fails
+/// `check_anomaly`'s temporal invariant checks. `Warn` (the default) only flags the item, so it
+/// shows up with a `** anomaly` marker wherever the playlist is printed. `Prune` additionally
+/// makes `prune` treat it as a deletion candidate. `Fail` aborts the run as soon as any anomaly is
+/// found, since a mis-dated stream could otherwise silently sort into the wrong tier. In dry-run
+/// mode `Fail` never aborts: a dry run only ever previews, so anomalies still just show up as
+/// `** anomaly` markers.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum AnomalyPolicy {
+    #[default]
+    Warn,
+    Prune,
+    Fail,
+}
+
+/// ReportFormat selects the serialization used for the item-error report written by `items()`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReportFormat {
+    Yaml,
+    Json,
+}
+
+/// SchedulePrivacy controls how much detail `schedule_html` includes. In public mode the
+/// rendered page is safe to share with viewers: only titles and times. In private mode it also
+/// links each title to its video and carries the `** blocked`/`** invalid` annotations `print`
+/// shows, for the channel owner's own use.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SchedulePrivacy {
+    Public,
+    Private,
 }
 
 /// new constructs a Playlist trait implementation for manipulating the playlist with the given playlist id.
 /// If dry-run is true, information will be printed out but the playlist will not be updated on YouTube.
 /// Debugging information is printed if and only if debug is true.
-pub fn new(hub: YouTube, id: &str, dry_run: bool, debug: bool) -> impl Playlist {
+/// `cache_path` is the JSON file used to remember video metadata between runs; pass `no_cache` to
+/// bypass it entirely (every video is re-fetched and the cache file is left untouched).
+/// `report_path`/`report_format` control where the per-item parse-error report is written.
+/// `anomaly_policy` controls what happens when a video fails its temporal invariant checks.
+/// Reads and writes both go through the given YouTube hub; use `new_with_source` to read through
+/// an alternative `MetadataSource` instead.
+pub fn new(
+    hub: YouTube,
+    id: &str,
+    dry_run: bool,
+    debug: bool,
+    cache_path: &str,
+    no_cache: bool,
+    report_path: &str,
+    report_format: ReportFormat,
+    anomaly_policy: AnomalyPolicy,
+) -> impl Playlist {
+    let source = Box::new(YouTubeSource::new(hub.clone()));
+    new_with_source(
+        hub,
+        source,
+        id,
+        dry_run,
+        debug,
+        cache_path,
+        no_cache,
+        report_path,
+        report_format,
+        anomaly_policy,
+    )
+}
+
+/// new_with_source is like `new`, but serves `items()`'s read path from `source` instead of the
+/// YouTube hub, e.g. a quota-free `InvidiousSource`. Playlist mutation (`sort`'s update, `prune`'s
+/// delete) always goes through `hub`, so a read-only source still needs a hub supplied. If
+/// `source.supports_mutation()` is `false`, `sort`/`prune` return `PlaylistError::ReadOnlySource`
+/// instead of mutating the playlist with bogus playlist item ids.
+pub fn new_with_source(
+    hub: YouTube,
+    source: Box<dyn MetadataSource + Send + Sync>,
+    id: &str,
+    dry_run: bool,
+    debug: bool,
+    cache_path: &str,
+    no_cache: bool,
+    report_path: &str,
+    report_format: ReportFormat,
+    anomaly_policy: AnomalyPolicy,
+) -> impl Playlist {
     PlaylistImpl {
         hub: hub,
+        source: source,
         id: id.to_owned(),
         dry_run: dry_run,
         debug: debug,
+        cache_path: PathBuf::from(cache_path),
+        no_cache: no_cache,
+        report_path: PathBuf::from(report_path),
+        report_format: report_format,
+        anomaly_policy: anomaly_policy,
+    }
+}
+
+fn write_report(path: &Path, format: ReportFormat, errs: &[ItemError]) -> std::io::Result<()> {
+    let data = match format {
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(errs).expect("report should always serialize")
+        }
+        ReportFormat::Yaml => {
+            serde_yaml::to_string(errs).expect("report should always serialize")
+        }
+    };
+    fs::write(path, data)
+}
+
+/// CachedItem holds the subset of `Item` fields that are worth remembering across runs: once a
+/// video has an `actual_start_time` it can never change, so it never needs re-fetching.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct CachedItem {
+    scheduled_start_time: Option<DateTime<FixedOffset>>,
+    actual_start_time: Option<DateTime<FixedOffset>>,
+    blocked: bool,
+}
+
+type Cache = HashMap<String, CachedItem>;
+
+fn load_cache(path: &Path) -> Cache {
+    match fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable cache file {:?}: {}", path, e);
+            Cache::new()
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Cache::new(),
+        Err(e) => {
+            eprintln!("Failed to read cache file {:?}: {}", path, e);
+            Cache::new()
+        }
+    }
+}
+
+/// save_cache writes the cache to a temporary file alongside `path` and renames it into place, so
+/// a run that is interrupted mid-write never leaves a corrupt cache file behind.
+fn save_cache(path: &Path, cache: &Cache) -> std::io::Result<()> {
+    let data = serde_json::to_string_pretty(cache).expect("cache should always serialize");
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// update_cache merges freshly-fetched video details into `cache`. Any id in `to_fetch` that
+/// didn't come back in `fetched` no longer exists (most likely deleted), so its stale entry is
+/// removed rather than left in place: otherwise the Item built from it would keep its old
+/// schedule instead of having no time info, and it would be re-fetched forever since it can never
+/// pick up an `actual_start_time`.
+fn update_cache(cache: &mut Cache, to_fetch: &[String], fetched: &HashMap<String, VideoDetails>) {
+    for (video_id, details) in fetched {
+        cache.insert(
+            video_id.clone(),
+            CachedItem {
+                scheduled_start_time: details.scheduled_start_time,
+                actual_start_time: details.actual_start_time,
+                blocked: details.blocked,
+            },
+        );
+    }
+    for video_id in to_fetch {
+        if !fetched.contains_key(video_id) {
+            cache.remove(video_id);
+        }
     }
 }
 
 #[async_trait]
 impl Playlist for PlaylistImpl {
     async fn items(self: &PlaylistImpl) -> Result<Vec<Item>> {
-        let mut list: Vec<Item> = vec![];
-
-        let (_, mut res) = playlist_items(&self.hub, &self.id, &None).await?;
-        while let Some(items) = &res.items {
-            for item in items {
-                let video_id = item
-                    .content_details
-                    .as_ref()
-                    .unwrap()
-                    .video_id
-                    .as_ref()
-                    .unwrap();
-
-                let (_, v) = self
-                    .hub
-                    .videos()
-                    .list(&vec![
-                        "liveStreamingDetails".into(),
-                        "contentDetails".into(),
-                    ])
-                    .add_id(video_id)
-                    .doit()
-                    .await?;
+        let mut cache = if self.no_cache {
+            Cache::new()
+        } else {
+            load_cache(&self.cache_path)
+        };
+
+        let mut errs: Vec<ItemError> = vec![];
+
+        let (entries, list_errs) = self.source.playlist_items(&self.id).await?;
+        errs.extend(list_errs);
+
+        // A cached entry is only trustworthy once the video has actually gone live: an actual
+        // start time is immutable, but a schedule (or its absence) can still change.
+        let to_fetch: Vec<String> = entries
+            .iter()
+            .filter(|entry| {
+                cache
+                    .get(&entry.video_id)
+                    .map_or(true, |cached| cached.actual_start_time.is_none())
+            })
+            .map(|entry| entry.video_id.clone())
+            .collect();
+
+        // Fetch live-streaming and content details for the videos the cache can't answer.
+        // Deleted videos are simply missing from the result, which leaves the corresponding
+        // Item with no time info so prune still removes it.
+        let (fetched, detail_errs) = if to_fetch.is_empty() {
+            (HashMap::new(), vec![])
+        } else {
+            self.source.video_details(&to_fetch).await?
+        };
+        errs.extend(detail_errs);
 
+        update_cache(&mut cache, &to_fetch, &fetched);
+
+        let list: Vec<Item> = entries
+            .into_iter()
+            .map(|entry| {
+                let normalized_title = normalize_title(&entry.title);
                 let mut it = Item {
-                    video_id: video_id.to_owned(),
-                    playlist_item_id: item.id.as_ref().unwrap().to_owned(),
-                    title: item
-                        .snippet
-                        .as_ref()
-                        .unwrap()
-                        .title
-                        .as_ref()
-                        .unwrap()
-                        .to_owned(),
+                    video_id: entry.video_id.clone(),
+                    playlist_item_id: entry.playlist_item_id,
+                    title: entry.title,
+                    normalized_title,
                     ..Default::default()
                 };
-
-                let videos = v.items.unwrap();
-
-                if videos.len() > 0 {
-                    let live_streaming_details =
-                        videos.get(0).unwrap().live_streaming_details.as_ref();
-                    if let Some(details) = live_streaming_details {
-                        it.scheduled_start_time = details
-                            .scheduled_start_time
-                            .as_ref()
-                            .map(|d| DateTime::parse_from_rfc3339(&d).unwrap());
-                        it.actual_start_time = details
-                            .actual_start_time
-                            .as_ref()
-                            .map(|d| DateTime::parse_from_rfc3339(&d).unwrap());
-                    }
-                    if let Some(content_details) = videos.get(0).unwrap().content_details.as_ref() {
-                        if let Some(restriction) = content_details.region_restriction.as_ref() {
-                            if let Some(blocked) = restriction.blocked.as_ref() {
-                                it.blocked = !blocked.is_empty();
-                            }
-                        }
-                    }
+                if let Some(details) = cache.get(&entry.video_id) {
+                    it.scheduled_start_time = details.scheduled_start_time;
+                    it.actual_start_time = details.actual_start_time;
+                    it.blocked = details.blocked;
                 }
-                list.push(it)
+                it.anomaly_reason = check_anomaly(&it);
+                it
+            })
+            .collect();
+
+        if !self.no_cache {
+            if let Err(e) = save_cache(&self.cache_path, &cache) {
+                eprintln!("Failed to write video-metadata cache {:?}: {}", self.cache_path, e);
             }
-            if res.next_page_token.is_some() {
-                res = playlist_items(&self.hub, &self.id, &res.next_page_token)
-                    .await?
-                    .1;
-            } else {
-                res.items = None;
+        }
+
+        if !errs.is_empty() {
+            eprintln!("{} item(s) failed to parse, see {:?}", errs.len(), self.report_path);
+            if let Err(e) = write_report(&self.report_path, self.report_format, &errs) {
+                eprintln!("Failed to write item-error report {:?}: {}", self.report_path, e);
+            }
+        } else if let Err(e) = fs::remove_file(&self.report_path) {
+            if e.kind() != ErrorKind::NotFound {
+                eprintln!("Failed to remove stale item-error report {:?}: {}", self.report_path, e);
+            }
+        }
+
+        if !self.dry_run && self.anomaly_policy == AnomalyPolicy::Fail {
+            if let Some(bad) = list.iter().find(|i| i.anomaly_reason.is_some()) {
+                return Err(PlaylistError::Anomaly(format!(
+                    "{}: {}",
+                    bad,
+                    bad.anomaly_reason.as_ref().unwrap()
+                )));
             }
         }
 
@@ -146,6 +395,9 @@ impl Playlist for PlaylistImpl {
     }
 
     async fn sort(self: &Self) -> Result<()> {
+        if !self.source.supports_mutation() {
+            return Err(PlaylistError::ReadOnlySource);
+        }
         let mut items = self.items().await?;
         let original_items = items.clone();
         sort_items(&mut items);
@@ -190,14 +442,20 @@ impl Playlist for PlaylistImpl {
         self.sort().await?;
         let mut n = 0;
         for i in self.items().await? {
-            if i.blocked {
+            let anomalous = self.anomaly_policy == AnomalyPolicy::Prune && i.anomaly_reason.is_some();
+            if i.blocked || anomalous {
+                let why = if i.blocked {
+                    "blocked"
+                } else {
+                    "anomalous"
+                };
                 if !self.dry_run {
-                    eprintln!("Deleting playlist item for blocked video {}", i);
+                    eprintln!("Deleting playlist item for {} video {}", why, i);
                     prune_item(&self.hub, i.playlist_item_id).await?;
                 } else {
                     eprintln!(
-                        "Non-dry run would delete playlist item for blocked video {}",
-                        i
+                        "Non-dry run would delete playlist item for {} video {}",
+                        why, i
                     );
                 }
             } else if i.actual_start_time.is_some() {
@@ -231,12 +489,18 @@ impl Playlist for PlaylistImpl {
     async fn print(self: &Self) -> Result<()> {
         print(self.items().await?)
     }
+
+    async fn schedule_html(self: &Self, privacy: SchedulePrivacy) -> Result<String> {
+        let mut items = self.items().await?;
+        sort_items(&mut items);
+        Ok(schedule_html(&items, privacy))
+    }
 }
 
 fn print(items: Vec<Item>) -> Result<()> {
     for video in items {
         eprintln!(
-            "{}: {} {:?} {:?} {} {}",
+            "{}: {} {:?} {:?} {} {} {}",
             video.video_id,
             video.title,
             video.scheduled_start_time,
@@ -246,7 +510,12 @@ fn print(items: Vec<Item>) -> Result<()> {
             } else {
                 ""
             },
-            if video.blocked { "** blocked" } else { "" }
+            if video.blocked { "** blocked" } else { "" },
+            if video.anomaly_reason.is_some() {
+                "** anomaly"
+            } else {
+                ""
+            }
         );
     }
     Ok(())
@@ -261,23 +530,85 @@ async fn prune_item(hub: &YouTube, playlist_item_id: String) -> Result<()> {
     Ok(())
 }
 
-async fn playlist_items(
-    hub: &YouTube,
-    playlist_id: &str,
-    next_page_token: &Option<String>,
-) -> Result<(Response<hyper::body::Body>, PlaylistItemListResponse)> {
-    let mut req = hub
-        .playlist_items()
-        .list(&vec![
-            "snippet".into(),
-            "id".into(),
-            "contentDetails".into(),
-        ])
-        .playlist_id(playlist_id);
-    if let Some(next) = next_page_token {
-        req = req.page_token(&next);
-    }
-    req.doit().await
+/// schedule_html renders `items` (expected to already be sorted) as a standalone HTML page: an
+/// agenda of upcoming streams grouped by day, soonest first, followed by a list of already
+/// streamed items, most recent first.
+fn schedule_html(items: &[Item], privacy: SchedulePrivacy) -> String {
+    let mut upcoming: Vec<&Item> = items
+        .iter()
+        .filter(|i| i.actual_start_time.is_none() && i.scheduled_start_time.is_some())
+        .collect();
+    upcoming.sort_by_key(|i| i.scheduled_start_time.unwrap());
+
+    let mut by_day: BTreeMap<NaiveDate, Vec<&Item>> = BTreeMap::new();
+    for item in upcoming {
+        by_day
+            .entry(item.scheduled_start_time.unwrap().date_naive())
+            .or_default()
+            .push(item);
+    }
+
+    let streamed: Vec<&Item> = items
+        .iter()
+        .filter(|i| i.actual_start_time.is_some())
+        .collect();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Stream schedule</title></head>\n<body>\n");
+
+    html.push_str("<h1>Upcoming</h1>\n");
+    for (day, day_items) in &by_day {
+        html.push_str(&format!("<h2>{}</h2>\n<ul>\n", day.format("%A %Y-%m-%d")));
+        for item in day_items {
+            html.push_str(&schedule_item_html(item, privacy));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("<h1>Already streamed</h1>\n<ul>\n");
+    for item in &streamed {
+        html.push_str(&schedule_item_html(item, privacy));
+    }
+    html.push_str("</ul>\n");
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn schedule_item_html(item: &Item, privacy: SchedulePrivacy) -> String {
+    let time = item
+        .scheduled_start_time
+        .or(item.actual_start_time)
+        .map(|t| t.format("%H:%M").to_string())
+        .unwrap_or_default();
+    let title = html_escape(&item.title);
+
+    match privacy {
+        SchedulePrivacy::Public => format!("<li>{} {}</li>\n", time, title),
+        SchedulePrivacy::Private => {
+            let mut annotations = String::new();
+            if item.scheduled_start_time.is_none() {
+                annotations.push_str(" ** invalid");
+            }
+            if item.blocked {
+                annotations.push_str(" ** blocked");
+            }
+            format!(
+                "<li>{} <a href=\"https://www.youtube.com/watch?v={}\">{}</a>{}</li>\n",
+                time,
+                html_escape(&item.video_id),
+                title,
+                annotations
+            )
+        }
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 fn sort_items(items: &mut Vec<Item>) {
@@ -312,12 +643,64 @@ fn sort_items(items: &mut Vec<Item>) {
             // Order unstreamed, scheduled items before unstreamed, unscheduled items
             Ordering::Greater
         } else {
-            // Leave the order of unstreamed, unscheduled items alone
-            Ordering::Equal
+            // Neither item has any time info: fall back to alphabetical order by normalized title
+            v.normalized_title.cmp(&w.normalized_title)
         }
     })
 }
 
+/// normalize_title lowercases `title` and strips a leading "a "/"an "/"the " so that items with
+/// no time information sort alphabetically in a way that ignores a leading English article.
+fn normalize_title(title: &str) -> String {
+    static LEADING_ARTICLE: OnceLock<Regex> = OnceLock::new();
+    let re = LEADING_ARTICLE.get_or_init(|| Regex::new(r"(?i)^(a|an|the)\s+").unwrap());
+    re.replace(&title.to_lowercase(), "").into_owned()
+}
+
+/// An implausibly far future/past start time likely means a malformed timestamp rather than a
+/// genuine stream; a year either side of "now" comfortably covers real scheduling horizons.
+const MAX_PLAUSIBLE_SKEW: Duration = Duration::days(366);
+
+/// check_anomaly verifies the temporal invariants a well-formed Item should satisfy, returning
+/// the reason the first violated invariant failed, if any:
+/// * `actual_start_time` must not precede `scheduled_start_time`
+/// * `actual_start_time` should not be present without a `scheduled_start_time`
+/// * neither timestamp should be implausibly far in the future or past
+fn check_anomaly(item: &Item) -> Option<String> {
+    match (item.scheduled_start_time, item.actual_start_time) {
+        (Some(scheduled), Some(actual)) if actual < scheduled => {
+            return Some(format!(
+                "actual_start_time {} precedes scheduled_start_time {}",
+                actual, scheduled
+            ));
+        }
+        (None, Some(actual)) => {
+            return Some(format!(
+                "actual_start_time {} present without a scheduled_start_time",
+                actual
+            ));
+        }
+        _ => {}
+    }
+
+    let now = Utc::now();
+    for (label, t) in [
+        ("scheduled_start_time", item.scheduled_start_time),
+        ("actual_start_time", item.actual_start_time),
+    ] {
+        if let Some(t) = t {
+            let skew = t.signed_duration_since(now);
+            if skew > MAX_PLAUSIBLE_SKEW {
+                return Some(format!("{} {} is implausibly far in the future", label, t));
+            }
+            if -skew > MAX_PLAUSIBLE_SKEW {
+                return Some(format!("{} {} is implausibly far in the past", label, t));
+            }
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -391,6 +774,175 @@ mod tests {
         assert_video_ids(v, vec!["v1", "v2"]);
     }
 
+    #[test]
+    fn sort_items_unstreamed_unscheduled_orders_by_normalized_title() {
+        let mut v = vec![
+            new_titled_item("v1", "The Zebra Stream"),
+            new_titled_item("v2", "An Apple a Day"),
+            new_titled_item("v3", "Bananas Galore"),
+        ];
+        sort_items(&mut v);
+        assert_video_ids(v, vec!["v2", "v3", "v1"]);
+    }
+
+    #[test]
+    fn check_anomaly_ok() {
+        // Dated relative to now: the fixed 2021 fixtures are beyond MAX_PLAUSIBLE_SKEW.
+        let mut i = new_item(1);
+        let now: DateTime<FixedOffset> = Utc::now().into();
+        i.scheduled_start_time = Some(now);
+        i.actual_start_time = Some(now + Duration::minutes(1));
+        assert_eq!(check_anomaly(&i), None);
+
+        let i = new_item(1);
+        assert_eq!(check_anomaly(&i), None);
+    }
+
+    #[test]
+    fn check_anomaly_actual_before_scheduled() {
+        let mut i = new_streamed_item(1);
+        i.actual_start_time = Some(
+            DateTime::parse_from_rfc3339("2021-09-30T10:54:00+01:00").unwrap(),
+        );
+        assert!(check_anomaly(&i).unwrap().contains("precedes"));
+    }
+
+    #[test]
+    fn check_anomaly_actual_without_scheduled() {
+        let mut i = new_item(1);
+        i.actual_start_time = Some(
+            DateTime::parse_from_rfc3339("2021-09-30T10:56:01+01:00").unwrap(),
+        );
+        assert!(check_anomaly(&i).unwrap().contains("without a scheduled_start_time"));
+    }
+
+    #[test]
+    fn check_anomaly_implausible_skew() {
+        let mut i = new_item(1);
+        i.scheduled_start_time = Some(Utc::now().into());
+        i.scheduled_start_time = Some(
+            i.scheduled_start_time.unwrap() - Duration::days(400),
+        );
+        assert!(check_anomaly(&i).unwrap().contains("implausibly far in the past"));
+    }
+
+    #[test]
+    fn update_cache_removes_stale_entry_for_deleted_video() {
+        let mut cache = Cache::new();
+        cache.insert(
+            "v1".to_owned(),
+            CachedItem {
+                scheduled_start_time: Some(
+                    DateTime::parse_from_rfc3339("2021-09-30T10:55:00+01:00").unwrap(),
+                ),
+                actual_start_time: None,
+                blocked: false,
+            },
+        );
+        // v1 was re-fetched (it had no actual_start_time yet) but is now missing from the result,
+        // i.e. it was deleted.
+        update_cache(&mut cache, &["v1".to_owned()], &HashMap::new());
+        assert!(cache.get("v1").is_none());
+    }
+
+    #[test]
+    fn update_cache_inserts_and_preserves_untouched_entries() {
+        let mut cache = Cache::new();
+        cache.insert(
+            "v1".to_owned(),
+            CachedItem {
+                scheduled_start_time: None,
+                actual_start_time: Some(
+                    DateTime::parse_from_rfc3339("2021-09-30T10:55:00+01:00").unwrap(),
+                ),
+                blocked: false,
+            },
+        );
+        let mut fetched = HashMap::new();
+        fetched.insert(
+            "v2".to_owned(),
+            VideoDetails {
+                scheduled_start_time: Some(
+                    DateTime::parse_from_rfc3339("2021-09-30T10:55:00+01:00").unwrap(),
+                ),
+                actual_start_time: None,
+                blocked: false,
+            },
+        );
+        update_cache(&mut cache, &["v2".to_owned()], &fetched);
+        // v1 wasn't in to_fetch at all (e.g. it already had an actual_start_time), so it must
+        // survive untouched.
+        assert!(cache.get("v1").is_some());
+        assert!(cache.get("v2").is_some());
+    }
+
+    #[test]
+    fn schedule_html_groups_upcoming_by_day_soonest_first() {
+        let items = vec![
+            new_titled_item_scheduled("v1", "Day Two Stream", "2021-10-02T09:00:00+00:00"),
+            new_titled_item_scheduled("v2", "Day One Late", "2021-10-01T18:00:00+00:00"),
+            new_titled_item_scheduled("v3", "Day One Early", "2021-10-01T09:00:00+00:00"),
+        ];
+        let html = schedule_html(&items, SchedulePrivacy::Public);
+
+        let day_one_pos = html.find("Friday 2021-10-01").unwrap();
+        let day_two_pos = html.find("Saturday 2021-10-02").unwrap();
+        let early_pos = html.find("Day One Early").unwrap();
+        let late_pos = html.find("Day One Late").unwrap();
+        assert!(day_one_pos < day_two_pos);
+        assert!(early_pos < late_pos);
+        assert!(late_pos < day_two_pos);
+    }
+
+    #[test]
+    fn schedule_html_lists_streamed_items() {
+        let mut streamed = new_streamed_item(1);
+        streamed.title = "Already Live".to_owned();
+        let html = schedule_html(&[streamed], SchedulePrivacy::Public);
+        assert!(html.contains("Already streamed"));
+        assert!(html.contains("Already Live"));
+    }
+
+    #[test]
+    fn schedule_item_html_public_omits_link_and_annotations() {
+        let mut item = new_item(1);
+        item.title = "A Title".to_owned();
+        item.blocked = true;
+        let html = schedule_item_html(&item, SchedulePrivacy::Public);
+        assert!(html.contains("A Title"));
+        assert!(!html.contains("youtube.com"));
+        assert!(!html.contains("** blocked"));
+        assert!(!html.contains("** invalid"));
+    }
+
+    #[test]
+    fn schedule_item_html_private_includes_link_and_annotations() {
+        let mut item = new_item(1);
+        item.title = "A Title".to_owned();
+        item.blocked = true;
+        let html = schedule_item_html(&item, SchedulePrivacy::Private);
+        assert!(html.contains(&format!("youtube.com/watch?v={}", item.video_id)));
+        assert!(html.contains("** blocked"));
+        assert!(html.contains("** invalid"));
+    }
+
+    #[test]
+    fn schedule_item_html_escapes_title() {
+        let mut item = new_item(1);
+        item.title = "<b>Bold</b> & \"Quoted\"".to_owned();
+        let html = schedule_item_html(&item, SchedulePrivacy::Public);
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(html.contains("&amp;"));
+        assert!(html.contains("&quot;"));
+    }
+
+    fn new_titled_item_scheduled(video_id: &str, title: &str, scheduled: &str) -> Item {
+        let mut i = new_titled_item(video_id, title);
+        i.scheduled_start_time = Some(DateTime::parse_from_rfc3339(scheduled).unwrap());
+        i
+    }
+
     fn new_scheduled_item(n: u32) -> Item {
         let mut i = new_item(n);
         i.scheduled_start_time =
@@ -407,10 +959,22 @@ mod tests {
 
     fn new_item(n: u32) -> Item {
         assert!(n <= 9);
+        let title = format!("video {}", n);
         Item {
             video_id: format!("v{}", n).to_owned(),
             playlist_item_id: format!("pii{}", n).to_owned(),
-            title: format!("video {}", n).to_owned(),
+            normalized_title: normalize_title(&title),
+            title,
+            ..Default::default()
+        }
+    }
+
+    fn new_titled_item(video_id: &str, title: &str) -> Item {
+        Item {
+            video_id: video_id.to_owned(),
+            playlist_item_id: format!("pii-{}", video_id),
+            normalized_title: normalize_title(title),
+            title: title.to_owned(),
             ..Default::default()
         }
     }